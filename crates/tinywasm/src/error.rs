@@ -0,0 +1,30 @@
+use core::fmt;
+
+/// Errors produced by the interpreter's value stack and its supporting
+/// fuel/limit machinery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A value was popped or peeked from an empty typed sub-stack.
+    StackUnderflow,
+    /// A push took a typed sub-stack past its configured limit (the pushed
+    /// value stays on the stack), or a call would exceed the call-depth limit.
+    StackOverflow,
+    /// The configured fuel budget was exhausted.
+    OutOfFuel,
+    /// A snapshot buffer was missing, truncated, or failed to decode.
+    InvalidSnapshot,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::StackUnderflow => write!(f, "value stack underflow"),
+            Error::StackOverflow => write!(f, "value stack overflow"),
+            Error::OutOfFuel => write!(f, "out of fuel"),
+            Error::InvalidSnapshot => write!(f, "invalid value stack snapshot"),
+        }
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
@@ -0,0 +1,2 @@
+pub(crate) mod stack;
+pub(crate) mod values;
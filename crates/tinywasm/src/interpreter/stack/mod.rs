@@ -0,0 +1,17 @@
+mod value_stack;
+
+pub(crate) use value_stack::ValueStack;
+
+use alloc::boxed::Box;
+
+use crate::interpreter::values::{Value128, Value32, Value64, ValueRef};
+
+/// The locals of a single active call frame, grouped by storage width to
+/// match [`ValueStack`]'s own layout.
+#[derive(Debug, Clone)]
+pub(crate) struct Locals {
+    pub(crate) locals_32: Box<[Value32]>,
+    pub(crate) locals_64: Box<[Value64]>,
+    pub(crate) locals_128: Box<[Value128]>,
+    pub(crate) locals_ref: Box<[ValueRef]>,
+}
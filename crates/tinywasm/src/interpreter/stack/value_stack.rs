@@ -1,7 +1,7 @@
 use alloc::vec::Vec;
 use tinywasm_types::{LocalCounts, ValType, WasmValue};
 
-use crate::{interpreter::values::*, Result};
+use crate::{interpreter::values::*, Error, Result};
 
 use super::Locals;
 pub(crate) const STACK_32_SIZE: usize = 1024 * 128;
@@ -9,21 +9,117 @@ pub(crate) const STACK_64_SIZE: usize = 1024 * 128;
 pub(crate) const STACK_128_SIZE: usize = 1024 * 128;
 pub(crate) const STACK_REF_SIZE: usize = 1024;
 
+// Status: blocked, not wired up. `ValueStack::set_fuel`/`consume_fuel` are
+// exercised directly by this file's own tests, but nothing in this tree
+// calls `consume_fuel` from an instruction dispatch loop (that loop lives in
+// the interpreter's executor, which isn't part of this snapshot) or exposes
+// `set_fuel` through an embedder-facing config. Metering only takes effect
+// once a call site is added there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Fuel {
+    steps_remaining: u64,
+}
+
+impl Fuel {
+    pub(crate) fn new(steps: u64) -> Self {
+        Self { steps_remaining: steps }
+    }
+
+    #[inline(always)]
+    pub(crate) fn consume(&mut self) -> Result<()> {
+        match self.steps_remaining.checked_sub(1) {
+            Some(remaining) => {
+                self.steps_remaining = remaining;
+                Ok(())
+            }
+            None => Err(Error::OutOfFuel),
+        }
+    }
+
+    pub(crate) fn remaining(&self) -> u64 {
+        self.steps_remaining
+    }
+
+    pub(crate) fn refuel(&mut self, additional: u64) {
+        self.steps_remaining = self.steps_remaining.saturating_add(additional);
+    }
+}
+
+// Status: half-wired. `enter_call` now runs from `pop_locals`, the one real
+// call-frame-entry site that lives in this file, so the call-depth check is
+// reachable. `exit_call` has no matching call-return teardown site in this
+// file (that lives with the rest of the call-stack/executor machinery,
+// outside this snapshot) and stays unreachable until one is added there.
+// `StackLimits`/`new_with_limits` also still need plumbing through an
+// embedder-facing interpreter configuration.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct StackLimits {
+    pub(crate) max_stack_32: usize,
+    pub(crate) max_stack_64: usize,
+    pub(crate) max_stack_128: usize,
+    pub(crate) max_stack_ref: usize,
+    pub(crate) max_call_depth: usize,
+}
+
+impl Default for StackLimits {
+    fn default() -> Self {
+        Self {
+            max_stack_32: STACK_32_SIZE,
+            max_stack_64: STACK_64_SIZE,
+            max_stack_128: STACK_128_SIZE,
+            max_stack_ref: STACK_REF_SIZE,
+            max_call_depth: 1024,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct ValueStack {
     pub(crate) stack_32: Vec<Value32>,
     pub(crate) stack_64: Vec<Value64>,
     pub(crate) stack_128: Vec<Value128>,
     pub(crate) stack_ref: Vec<ValueRef>,
+    fuel: Option<Fuel>,
+    limits: StackLimits,
+    call_depth: usize,
 }
 
 impl ValueStack {
     pub(crate) fn new() -> Self {
+        Self::new_with_limits(StackLimits::default())
+    }
+
+    pub(crate) fn new_with_limits(limits: StackLimits) -> Self {
         Self {
             stack_32: Vec::with_capacity(STACK_32_SIZE),
             stack_64: Vec::with_capacity(STACK_64_SIZE),
             stack_128: Vec::with_capacity(STACK_128_SIZE),
             stack_ref: Vec::with_capacity(STACK_REF_SIZE),
+            fuel: None,
+            limits,
+            call_depth: 0,
+        }
+    }
+
+    pub(crate) fn set_fuel(&mut self, steps: Option<u64>) {
+        self.fuel = steps.map(Fuel::new);
+    }
+
+    pub(crate) fn fuel_remaining(&self) -> Option<u64> {
+        self.fuel.map(|fuel| fuel.remaining())
+    }
+
+    pub(crate) fn refuel(&mut self, additional: u64) {
+        if let Some(fuel) = &mut self.fuel {
+            fuel.refuel(additional);
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn consume_fuel(&mut self) -> Result<()> {
+        match &mut self.fuel {
+            Some(fuel) => fuel.consume(),
+            None => Ok(()),
         }
     }
 
@@ -41,43 +137,88 @@ impl ValueStack {
         T::stack_peek(self)
     }
 
+    #[inline]
+    pub(crate) fn peek_mut<T: InternalValue>(&mut self) -> Result<&mut T> {
+        T::stack_peek_mut(self)
+    }
+
     #[inline]
     pub(crate) fn pop<T: InternalValue>(&mut self) -> Result<T> {
         T::stack_pop(self)
     }
 
     #[inline]
-    pub(crate) fn push<T: InternalValue>(&mut self, value: T) {
-        T::stack_push(self, value)
+    pub(crate) fn push<T: InternalValue>(&mut self, value: T) -> Result<()> {
+        T::stack_push(self, value);
+        self.check_limits()
+    }
+
+    #[inline]
+    fn check_limits(&self) -> Result<()> {
+        if self.stack_32.len() > self.limits.max_stack_32
+            || self.stack_64.len() > self.limits.max_stack_64
+            || self.stack_128.len() > self.limits.max_stack_128
+            || self.stack_ref.len() > self.limits.max_stack_ref
+        {
+            return Err(Error::StackOverflow);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn enter_call(&mut self) -> Result<()> {
+        if self.call_depth >= self.limits.max_call_depth {
+            return Err(Error::StackOverflow);
+        }
+        self.call_depth += 1;
+        Ok(())
+    }
+
+    pub(crate) fn exit_call(&mut self) {
+        self.call_depth = self.call_depth.saturating_sub(1);
     }
 
     pub(crate) fn drop<T: InternalValue>(&mut self) -> Result<()> {
         T::stack_pop(self).map(|_| ())
     }
 
-    // TODO: this needs to re-introduce the top replacement optimization
+    // `val1` (the slot we may overwrite) is never popped: on cond == 0 we
+    // write `val2` over it in place, otherwise it's already the correct result.
     pub(crate) fn select<T: InternalValue>(&mut self) -> Result<()> {
         let cond: i32 = self.pop()?;
         let val2: T = self.pop()?;
         if cond == 0 {
-            self.drop::<T>()?;
-            self.push(val2);
+            *self.peek_mut::<T>()? = val2;
         }
         Ok(())
     }
 
-    // TODO: this needs to re-introduce the top replacement optimization
+    // Cross-width ops (e.g. i64.eq, two i64s in, one i32 out): slot layouts differ, so there's
+    // no way to overwrite in place; pop both operands and push the result.
     pub(crate) fn calculate<T: InternalValue, U: InternalValue>(&mut self, func: fn(T, T) -> Result<U>) -> Result<()> {
-        let v2 = T::stack_pop(self)?;
-        let v1 = T::stack_pop(self)?;
-        U::stack_push(self, func(v1, v2)?);
+        let v2: T = self.pop()?;
+        let v1: T = self.pop()?;
+        self.push(func(v1, v2)?)
+    }
+
+    // Same-type ops (the common case: iadd, fmul, ...) overwrite `val1`'s slot
+    // in place instead of popping it and pushing the result.
+    pub(crate) fn calculate_same<T: InternalValue>(&mut self, func: fn(T, T) -> Result<T>) -> Result<()> {
+        let v2: T = self.pop()?;
+        let v1: T = self.peek()?;
+        *self.peek_mut::<T>()? = func(v1, v2)?;
         Ok(())
     }
 
-    // TODO: this needs to re-introduce the top replacement optimization
+    // See `calculate` above; the unary equivalent.
     pub(crate) fn replace_top<T: InternalValue, U: InternalValue>(&mut self, func: fn(T) -> Result<U>) -> Result<()> {
-        let v1 = T::stack_pop(self)?;
-        U::stack_push(self, func(v1)?);
+        let v1: T = self.pop()?;
+        self.push(func(v1)?)
+    }
+
+    // See `calculate_same` above; the unary equivalent.
+    pub(crate) fn replace_top_same<T: InternalValue>(&mut self, func: fn(T) -> Result<T>) -> Result<()> {
+        let v1: T = self.peek()?;
+        *self.peek_mut::<T>()? = func(v1)?;
         Ok(())
     }
 
@@ -105,7 +246,12 @@ impl ValueStack {
     }
 
     // TODO: a lot of optimization potential here
+    //
+    // Entering a new call frame's locals is this file's one real call-entry
+    // site, so the call-depth limit is enforced here; there's no matching
+    // call-return teardown site in this file to pair it with `exit_call`.
     pub(crate) fn pop_locals(&mut self, val_types: &[ValType], lc: LocalCounts) -> Result<Locals> {
+        self.enter_call()?;
         let mut locals_32 = Vec::new();
         locals_32.reserve_exact(lc.local_32 as usize);
         let mut locals_64 = Vec::new();
@@ -156,13 +302,16 @@ impl ValueStack {
         truncate_keep(&mut self.stack_ref, to.sref, keep.sref as u32);
     }
 
-    pub(crate) fn push_dyn(&mut self, value: TinyWasmValue) {
+    // Returns `Result` now that pushing enforces `StackLimits`; callers
+    // outside this file (not part of this snapshot) need to propagate it.
+    pub(crate) fn push_dyn(&mut self, value: TinyWasmValue) -> Result<()> {
         match value {
             TinyWasmValue::Value32(v) => self.stack_32.push(v),
             TinyWasmValue::Value64(v) => self.stack_64.push(v),
             TinyWasmValue::Value128(v) => self.stack_128.push(v),
             TinyWasmValue::ValueRef(v) => self.stack_ref.push(v),
         }
+        self.check_limits()
     }
 
     pub(crate) fn pop_wasmvalue(&mut self, val_type: ValType) -> Result<WasmValue> {
@@ -183,9 +332,268 @@ impl ValueStack {
         }
     }
 
-    pub(crate) fn extend_from_wasmvalues(&mut self, values: &[WasmValue]) {
+    pub(crate) fn extend_from_wasmvalues(&mut self, values: &[WasmValue]) -> Result<()> {
         for value in values.iter() {
-            self.push_dyn(value.into())
+            self.push_dyn(value.into())?;
+        }
+        Ok(())
+    }
+
+    /// Serializes this `ValueStack`'s four typed sub-stacks into a compact,
+    /// versioned buffer.
+    ///
+    /// Each sub-stack is encoded through its own stable little-endian
+    /// representation (not a native-layout memcpy), so the result is
+    /// portable across hosts; [`Self::restore`] reconstructs an equivalent
+    /// [`ValueStack`] (with default [`StackLimits`] and no fuel).
+    ///
+    /// Scope: this covers only the operand stack owned by `ValueStack`
+    /// itself. A full pause/resume of a running instance also needs the
+    /// active call frames' [`Locals`] and frame metadata, which live on the
+    /// call-stack/executor side and aren't part of this file; pairing this
+    /// with a snapshot there is left to that type.
+    pub(crate) fn snapshot(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.push(SNAPSHOT_VERSION);
+        write_values(&mut buf, &self.stack_32, |v: Value32| v.to_le_bytes());
+        write_values(&mut buf, &self.stack_64, |v: Value64| v.to_le_bytes());
+        write_values(&mut buf, &self.stack_128, |v: Value128| v.to_le_bytes());
+        write_values(&mut buf, &self.stack_ref, write_value_ref);
+        buf
+    }
+
+    /// Reconstructs a [`ValueStack`] from a buffer produced by [`Self::snapshot`].
+    pub(crate) fn restore(buf: &[u8]) -> Result<Self> {
+        let mut cursor = buf;
+        if read_u8(&mut cursor)? != SNAPSHOT_VERSION {
+            return Err(Error::InvalidSnapshot);
         }
+
+        let stack_32 = read_values(&mut cursor, Value32::from_le_bytes)?;
+        let stack_64 = read_values(&mut cursor, Value64::from_le_bytes)?;
+        let stack_128 = read_values(&mut cursor, Value128::from_le_bytes)?;
+        let stack_ref = read_values(&mut cursor, read_value_ref)?;
+
+        // Built directly rather than via `..Self::new()`, which would also
+        // allocate four full-capacity `Vec`s just to discard them immediately.
+        Ok(Self { stack_32, stack_64, stack_128, stack_ref, fuel: None, limits: StackLimits::default(), call_depth: 0 })
+    }
+}
+
+const SNAPSHOT_VERSION: u8 = 1;
+
+// `ValueRef` is an `Option<u32>`-shaped reference handle: unlike the plain
+// numeric sub-stacks, a bit pattern read back from an untrusted buffer isn't
+// automatically a valid value of this type, so it's encoded as an explicit,
+// validated tag byte plus payload rather than transmuted.
+fn write_value_ref(v: ValueRef) -> [u8; 5] {
+    let mut out = [0u8; 5];
+    if let Some(idx) = v {
+        out[0] = 1;
+        out[1..5].copy_from_slice(&idx.to_le_bytes());
+    }
+    out
+}
+
+fn read_value_ref(bytes: [u8; 5]) -> Option<ValueRef> {
+    match bytes[0] {
+        0 => Some(None),
+        1 => Some(Some(u32::from_le_bytes(bytes[1..5].try_into().expect("len checked by array size")))),
+        _ => None,
+    }
+}
+
+fn write_values<T: Copy, const N: usize>(buf: &mut Vec<u8>, data: &[T], to_bytes: fn(T) -> [u8; N]) {
+    buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buf.push(N as u8);
+    for &value in data {
+        buf.extend_from_slice(&to_bytes(value));
+    }
+}
+
+fn read_values<T, const N: usize>(cursor: &mut &[u8], from_bytes: fn([u8; N]) -> Option<T>) -> Result<Vec<T>> {
+    let len = read_u32(cursor)? as usize;
+    let width = read_u8(cursor)? as usize;
+    if width != N {
+        return Err(Error::InvalidSnapshot);
+    }
+
+    let byte_len = len.checked_mul(N).ok_or(Error::InvalidSnapshot)?;
+    if cursor.len() < byte_len {
+        return Err(Error::InvalidSnapshot);
+    }
+    let (head, tail) = cursor.split_at(byte_len);
+    *cursor = tail;
+
+    let mut out = Vec::with_capacity(len);
+    for chunk in head.chunks_exact(N) {
+        let bytes: [u8; N] = chunk.try_into().expect("chunks_exact yields N-byte slices");
+        out.push(from_bytes(bytes).ok_or(Error::InvalidSnapshot)?);
+    }
+    Ok(out)
+}
+
+fn read_u8(cursor: &mut &[u8]) -> Result<u8> {
+    let (byte, tail) = cursor.split_first().ok_or(Error::InvalidSnapshot)?;
+    *cursor = tail;
+    Ok(*byte)
+}
+
+fn read_u32(cursor: &mut &[u8]) -> Result<u32> {
+    if cursor.len() < 4 {
+        return Err(Error::InvalidSnapshot);
+    }
+    let (head, tail) = cursor.split_at(4);
+    *cursor = tail;
+    Ok(u32::from_le_bytes(head.try_into().expect("checked above")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuel_traps_once_exhausted() {
+        let mut fuel = Fuel::new(2);
+        assert!(fuel.consume().is_ok());
+        assert!(fuel.consume().is_ok());
+        assert!(matches!(fuel.consume(), Err(Error::OutOfFuel)));
+        assert_eq!(fuel.remaining(), 0);
+
+        fuel.refuel(1);
+        assert!(fuel.consume().is_ok());
+    }
+
+    #[test]
+    fn value_stack_fuel_api_meters_and_refuels() {
+        let mut stack = ValueStack::new();
+        assert_eq!(stack.fuel_remaining(), None);
+        stack.consume_fuel().unwrap(); // no budget set: unmetered, always ok
+
+        stack.set_fuel(Some(2));
+        assert_eq!(stack.fuel_remaining(), Some(2));
+        stack.consume_fuel().unwrap();
+        stack.consume_fuel().unwrap();
+        assert!(matches!(stack.consume_fuel(), Err(Error::OutOfFuel)));
+
+        stack.refuel(3);
+        assert_eq!(stack.fuel_remaining(), Some(3));
+        stack.consume_fuel().unwrap();
+    }
+
+    #[test]
+    fn push_traps_once_stack_limit_reached() {
+        let limits = StackLimits { max_stack_32: 2, ..StackLimits::default() };
+        let mut stack = ValueStack::new_with_limits(limits);
+        stack.push::<i32>(1).unwrap();
+        stack.push::<i32>(2).unwrap();
+        assert!(matches!(stack.push::<i32>(3), Err(Error::StackOverflow)));
+    }
+
+    #[test]
+    fn call_depth_traps_without_inflating_and_exit_never_underflows() {
+        let limits = StackLimits { max_call_depth: 1, ..StackLimits::default() };
+        let mut stack = ValueStack::new_with_limits(limits);
+        stack.enter_call().unwrap();
+        assert!(matches!(stack.enter_call(), Err(Error::StackOverflow)));
+
+        // the failed enter_call left the depth at 1, so one exit_call frees the slot
+        stack.exit_call();
+        assert!(stack.enter_call().is_ok());
+
+        stack.exit_call();
+        stack.exit_call(); // unmatched exit_call must saturate, not underflow/panic
+        assert!(stack.enter_call().is_ok());
+    }
+
+    #[test]
+    fn pop_locals_enforces_call_depth_limit() {
+        let limits = StackLimits { max_call_depth: 1, ..StackLimits::default() };
+        let mut stack = ValueStack::new_with_limits(limits);
+        let lc = LocalCounts { local_32: 0, local_64: 0, local_128: 0, local_ref: 0 };
+
+        assert!(stack.pop_locals(&[], lc).is_ok());
+        assert!(matches!(stack.pop_locals(&[], lc), Err(Error::StackOverflow)));
+    }
+
+    #[test]
+    fn snapshot_restore_round_trips() {
+        let mut stack = ValueStack::new();
+        stack.push::<i32>(1).unwrap();
+        stack.push::<i32>(2).unwrap();
+        stack.push::<i64>(3).unwrap();
+        stack.push_dyn(TinyWasmValue::ValueRef(Some(7))).unwrap();
+        stack.push_dyn(TinyWasmValue::ValueRef(None)).unwrap();
+
+        let restored = ValueStack::restore(&stack.snapshot()).unwrap();
+        assert_eq!(restored.height().s32, stack.height().s32);
+        assert_eq!(restored.height().s64, stack.height().s64);
+        assert_eq!(restored.stack_ref, stack.stack_ref);
+    }
+
+    #[test]
+    fn select_picks_val1_on_nonzero_cond_and_val2_on_zero() {
+        let mut stack = ValueStack::new();
+        stack.push::<i32>(11).unwrap();
+        stack.push::<i32>(22).unwrap();
+        stack.push::<i32>(1).unwrap();
+        stack.select::<i32>().unwrap();
+        assert_eq!(stack.pop::<i32>().unwrap(), 11);
+
+        stack.push::<i32>(11).unwrap();
+        stack.push::<i32>(22).unwrap();
+        stack.push::<i32>(0).unwrap();
+        stack.select::<i32>().unwrap();
+        assert_eq!(stack.pop::<i32>().unwrap(), 22);
+    }
+
+    #[test]
+    fn calculate_same_overwrites_in_place() {
+        let mut stack = ValueStack::new();
+        stack.push::<i32>(3).unwrap();
+        stack.push::<i32>(4).unwrap();
+        stack.calculate_same::<i32>(|a, b| Ok(a + b)).unwrap();
+        assert_eq!(stack.height().s32, 1);
+        assert_eq!(stack.pop::<i32>().unwrap(), 7);
+    }
+
+    #[test]
+    fn calculate_handles_cross_width_result() {
+        let mut stack = ValueStack::new();
+        stack.push::<i32>(2).unwrap();
+        stack.push::<i32>(3).unwrap();
+        stack.calculate::<i32, i64>(|a, b| Ok((a + b) as i64)).unwrap();
+        assert_eq!(stack.height().s32, 0);
+        assert_eq!(stack.pop::<i64>().unwrap(), 5);
+    }
+
+    #[test]
+    fn replace_top_same_overwrites_in_place() {
+        let mut stack = ValueStack::new();
+        stack.push::<i32>(5).unwrap();
+        stack.replace_top_same::<i32>(|a| Ok(-a)).unwrap();
+        assert_eq!(stack.height().s32, 1);
+        assert_eq!(stack.pop::<i32>().unwrap(), -5);
+    }
+
+    #[test]
+    fn replace_top_handles_cross_width_result() {
+        let mut stack = ValueStack::new();
+        stack.push::<i32>(9).unwrap();
+        stack.replace_top::<i32, i64>(|a| Ok(a as i64)).unwrap();
+        assert_eq!(stack.height().s32, 0);
+        assert_eq!(stack.pop::<i64>().unwrap(), 9);
+    }
+
+    #[test]
+    fn restore_rejects_corrupted_or_truncated_buffer() {
+        let mut stack = ValueStack::new();
+        stack.push::<i32>(42).unwrap();
+        let mut buf = stack.snapshot();
+
+        assert!(matches!(ValueStack::restore(&buf[..buf.len() - 1]), Err(Error::InvalidSnapshot)));
+
+        buf[0] = SNAPSHOT_VERSION.wrapping_add(1);
+        assert!(matches!(ValueStack::restore(&buf), Err(Error::InvalidSnapshot)));
     }
 }
@@ -0,0 +1,302 @@
+use crate::interpreter::stack::ValueStack;
+use crate::{Error, Result};
+
+/// Raw contents of one slot in [`ValueStack`]'s 32-bit sub-stack; every i32,
+/// u32 and f32 value is stored as this bit pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub(crate) struct Value32(u32);
+
+impl Value32 {
+    pub(crate) fn to_le_bytes(self) -> [u8; 4] {
+        self.0.to_le_bytes()
+    }
+
+    pub(crate) fn from_le_bytes(bytes: [u8; 4]) -> Option<Self> {
+        Some(Self(u32::from_le_bytes(bytes)))
+    }
+}
+
+impl From<i32> for Value32 {
+    fn from(v: i32) -> Self {
+        Self(v as u32)
+    }
+}
+impl From<Value32> for i32 {
+    fn from(v: Value32) -> Self {
+        v.0 as i32
+    }
+}
+impl From<f32> for Value32 {
+    fn from(v: f32) -> Self {
+        Self(v.to_bits())
+    }
+}
+impl From<Value32> for f32 {
+    fn from(v: Value32) -> Self {
+        f32::from_bits(v.0)
+    }
+}
+
+/// Raw contents of one slot in [`ValueStack`]'s 64-bit sub-stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub(crate) struct Value64(u64);
+
+impl Value64 {
+    pub(crate) fn to_le_bytes(self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+
+    pub(crate) fn from_le_bytes(bytes: [u8; 8]) -> Option<Self> {
+        Some(Self(u64::from_le_bytes(bytes)))
+    }
+}
+
+impl From<i64> for Value64 {
+    fn from(v: i64) -> Self {
+        Self(v as u64)
+    }
+}
+impl From<Value64> for i64 {
+    fn from(v: Value64) -> Self {
+        v.0 as i64
+    }
+}
+impl From<f64> for Value64 {
+    fn from(v: f64) -> Self {
+        Self(v.to_bits())
+    }
+}
+impl From<Value64> for f64 {
+    fn from(v: Value64) -> Self {
+        f64::from_bits(v.0)
+    }
+}
+
+/// Raw contents of one slot in [`ValueStack`]'s 128-bit sub-stack (v128).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub(crate) struct Value128(u128);
+
+impl Value128 {
+    pub(crate) fn to_le_bytes(self) -> [u8; 16] {
+        self.0.to_le_bytes()
+    }
+
+    pub(crate) fn from_le_bytes(bytes: [u8; 16]) -> Option<Self> {
+        Some(Self(u128::from_le_bytes(bytes)))
+    }
+}
+
+impl From<u128> for Value128 {
+    fn from(v: u128) -> Self {
+        Self(v)
+    }
+}
+impl From<Value128> for u128 {
+    fn from(v: Value128) -> Self {
+        v.0
+    }
+}
+
+/// A function or extern reference handle; `None` is a null reference.
+pub(crate) type ValueRef = Option<u32>;
+
+/// A popped value whose concrete width was only known at runtime (see
+/// [`ValueStack::pop_dyn`]).
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum TinyWasmValue {
+    Value32(Value32),
+    Value64(Value64),
+    Value128(Value128),
+    ValueRef(ValueRef),
+}
+
+/// The four typed sub-stack lengths at some point in time, used to restore a
+/// saved stack location (see [`ValueStack::height`]/[`ValueStack::truncate_keep`]).
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct StackLocation {
+    pub(crate) s32: u32,
+    pub(crate) s64: u32,
+    pub(crate) s128: u32,
+    pub(crate) sref: u32,
+}
+
+/// Number of values per sub-stack that a control-flow block keeps on top of
+/// the stack when it's unwound (its result values). A block's arity fits
+/// comfortably in 16 bits; [`ValueStack::truncate_keep`] widens each field
+/// to `u32` to match the sub-stack lengths it's compared against.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct StackHeight {
+    pub(crate) s32: u16,
+    pub(crate) s64: u16,
+    pub(crate) s128: u16,
+    pub(crate) sref: u16,
+}
+
+/// A value that can be pushed to, popped from, and peeked in place on a
+/// [`ValueStack`]. Implemented once per logical wasm value type (`i32`,
+/// `f32`, ...) plus once per raw sub-stack slot type (`Value32`, ...) for the
+/// runtime-typed [`TinyWasmValue`] path.
+pub(crate) trait InternalValue: Sized {
+    fn stack_peek(stack: &ValueStack) -> Result<Self>;
+    fn stack_peek_mut(stack: &mut ValueStack) -> Result<&mut Self>;
+    fn stack_pop(stack: &mut ValueStack) -> Result<Self>;
+    fn stack_push(stack: &mut ValueStack, value: Self);
+}
+
+impl InternalValue for Value32 {
+    fn stack_peek(stack: &ValueStack) -> Result<Self> {
+        stack.stack_32.last().copied().ok_or(Error::StackUnderflow)
+    }
+    fn stack_peek_mut(stack: &mut ValueStack) -> Result<&mut Self> {
+        stack.stack_32.last_mut().ok_or(Error::StackUnderflow)
+    }
+    fn stack_pop(stack: &mut ValueStack) -> Result<Self> {
+        stack.stack_32.pop().ok_or(Error::StackUnderflow)
+    }
+    fn stack_push(stack: &mut ValueStack, value: Self) {
+        stack.stack_32.push(value);
+    }
+}
+
+impl InternalValue for Value64 {
+    fn stack_peek(stack: &ValueStack) -> Result<Self> {
+        stack.stack_64.last().copied().ok_or(Error::StackUnderflow)
+    }
+    fn stack_peek_mut(stack: &mut ValueStack) -> Result<&mut Self> {
+        stack.stack_64.last_mut().ok_or(Error::StackUnderflow)
+    }
+    fn stack_pop(stack: &mut ValueStack) -> Result<Self> {
+        stack.stack_64.pop().ok_or(Error::StackUnderflow)
+    }
+    fn stack_push(stack: &mut ValueStack, value: Self) {
+        stack.stack_64.push(value);
+    }
+}
+
+impl InternalValue for Value128 {
+    fn stack_peek(stack: &ValueStack) -> Result<Self> {
+        stack.stack_128.last().copied().ok_or(Error::StackUnderflow)
+    }
+    fn stack_peek_mut(stack: &mut ValueStack) -> Result<&mut Self> {
+        stack.stack_128.last_mut().ok_or(Error::StackUnderflow)
+    }
+    fn stack_pop(stack: &mut ValueStack) -> Result<Self> {
+        stack.stack_128.pop().ok_or(Error::StackUnderflow)
+    }
+    fn stack_push(stack: &mut ValueStack, value: Self) {
+        stack.stack_128.push(value);
+    }
+}
+
+impl InternalValue for ValueRef {
+    fn stack_peek(stack: &ValueStack) -> Result<Self> {
+        stack.stack_ref.last().copied().ok_or(Error::StackUnderflow)
+    }
+    fn stack_peek_mut(stack: &mut ValueStack) -> Result<&mut Self> {
+        stack.stack_ref.last_mut().ok_or(Error::StackUnderflow)
+    }
+    fn stack_pop(stack: &mut ValueStack) -> Result<Self> {
+        stack.stack_ref.pop().ok_or(Error::StackUnderflow)
+    }
+    fn stack_push(stack: &mut ValueStack, value: Self) {
+        stack.stack_ref.push(value);
+    }
+}
+
+// `i32`/`f32`/`i64`/`f64`/`v128` share their sub-stack's slot with its
+// `Value*` raw form; `stack_peek`/`stack_pop`/`stack_push` go through the
+// cheap `From` conversions above, but `stack_peek_mut` has to hand back a
+// reference into the existing slot rather than a fresh value, which needs a
+// pointer reinterpretation since the slot is physically a `Value32`/`Value64`/
+// `Value128`.
+
+impl InternalValue for i32 {
+    fn stack_peek(stack: &ValueStack) -> Result<Self> {
+        Value32::stack_peek(stack).map(Into::into)
+    }
+    fn stack_peek_mut(stack: &mut ValueStack) -> Result<&mut Self> {
+        let slot = Value32::stack_peek_mut(stack)?;
+        // SAFETY: `Value32` is `#[repr(transparent)]` around a `u32`, and
+        // every `u32` bit pattern is a valid `i32`, so reinterpreting the
+        // slot in place is sound.
+        Ok(unsafe { &mut *(slot as *mut Value32 as *mut i32) })
+    }
+    fn stack_pop(stack: &mut ValueStack) -> Result<Self> {
+        Value32::stack_pop(stack).map(Into::into)
+    }
+    fn stack_push(stack: &mut ValueStack, value: Self) {
+        Value32::stack_push(stack, value.into())
+    }
+}
+
+impl InternalValue for f32 {
+    fn stack_peek(stack: &ValueStack) -> Result<Self> {
+        Value32::stack_peek(stack).map(Into::into)
+    }
+    fn stack_peek_mut(stack: &mut ValueStack) -> Result<&mut Self> {
+        let slot = Value32::stack_peek_mut(stack)?;
+        // SAFETY: see the `i32` impl above; every `u32` bit pattern
+        // (including NaNs) is also a valid `f32`.
+        Ok(unsafe { &mut *(slot as *mut Value32 as *mut f32) })
+    }
+    fn stack_pop(stack: &mut ValueStack) -> Result<Self> {
+        Value32::stack_pop(stack).map(Into::into)
+    }
+    fn stack_push(stack: &mut ValueStack, value: Self) {
+        Value32::stack_push(stack, value.into())
+    }
+}
+
+impl InternalValue for i64 {
+    fn stack_peek(stack: &ValueStack) -> Result<Self> {
+        Value64::stack_peek(stack).map(Into::into)
+    }
+    fn stack_peek_mut(stack: &mut ValueStack) -> Result<&mut Self> {
+        let slot = Value64::stack_peek_mut(stack)?;
+        // SAFETY: see the `i32` impl above, for the 64-bit slot.
+        Ok(unsafe { &mut *(slot as *mut Value64 as *mut i64) })
+    }
+    fn stack_pop(stack: &mut ValueStack) -> Result<Self> {
+        Value64::stack_pop(stack).map(Into::into)
+    }
+    fn stack_push(stack: &mut ValueStack, value: Self) {
+        Value64::stack_push(stack, value.into())
+    }
+}
+
+impl InternalValue for f64 {
+    fn stack_peek(stack: &ValueStack) -> Result<Self> {
+        Value64::stack_peek(stack).map(Into::into)
+    }
+    fn stack_peek_mut(stack: &mut ValueStack) -> Result<&mut Self> {
+        let slot = Value64::stack_peek_mut(stack)?;
+        // SAFETY: see the `i32` impl above, for the 64-bit slot.
+        Ok(unsafe { &mut *(slot as *mut Value64 as *mut f64) })
+    }
+    fn stack_pop(stack: &mut ValueStack) -> Result<Self> {
+        Value64::stack_pop(stack).map(Into::into)
+    }
+    fn stack_push(stack: &mut ValueStack, value: Self) {
+        Value64::stack_push(stack, value.into())
+    }
+}
+
+impl InternalValue for u128 {
+    fn stack_peek(stack: &ValueStack) -> Result<Self> {
+        Value128::stack_peek(stack).map(Into::into)
+    }
+    fn stack_peek_mut(stack: &mut ValueStack) -> Result<&mut Self> {
+        let slot = Value128::stack_peek_mut(stack)?;
+        // SAFETY: see the `i32` impl above, for the 128-bit slot.
+        Ok(unsafe { &mut *(slot as *mut Value128 as *mut u128) })
+    }
+    fn stack_pop(stack: &mut ValueStack) -> Result<Self> {
+        Value128::stack_pop(stack).map(Into::into)
+    }
+    fn stack_push(stack: &mut ValueStack, value: Self) {
+        Value128::stack_push(stack, value.into())
+    }
+}
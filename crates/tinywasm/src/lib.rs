@@ -0,0 +1,8 @@
+#![no_std]
+
+extern crate alloc;
+
+mod error;
+pub(crate) mod interpreter;
+
+pub use error::{Error, Result};